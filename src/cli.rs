@@ -28,6 +28,18 @@ pub struct Cli {
     #[arg(long, short='x')]
     pub exclude_regex: Vec<String>,
 
+    /// Override the expiry for items matching a regex, given as
+    /// `PATTERN=SECONDS` (may be used more then once). The first matching
+    /// rule wins, so list more specific patterns first.
+    #[arg(long)]
+    pub expiry_rule: Vec<String>,
+
+    /// Treat items matching this regex as sensitive: their contents are
+    /// zeroed from memory on expiry and never written to the logs (may be
+    /// used more then once).
+    #[arg(long)]
+    pub sensitive_regex: Vec<String>,
+
     /// Log more verbosely. Use up to three times for increasingly verbose output.
     #[arg(long, short, action = clap::ArgAction::Count)]
     pub verbose: u8,
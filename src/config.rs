@@ -16,6 +16,16 @@ pub struct FileConfig {
     pub never_expire_regex: Vec<String>,
     #[serde(default)]
     pub exclude_regex: Vec<String>,
+    #[serde(default)]
+    pub expiry_rules: Vec<ExpiryRule>,
+    #[serde(default)]
+    pub sensitive_regex: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExpiryRule {
+    pub pattern: String,
+    pub seconds: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -24,6 +34,8 @@ pub struct Config {
     pub resync: Duration,
     pub exclude: Vec<Regex>,
     pub always_remove: Vec<Regex>,
+    pub expiry_rules: Vec<(Regex, Duration)>,
+    pub sensitive: Vec<Regex>,
 }
 
 impl Config {
@@ -65,16 +77,57 @@ impl Config {
             bail!("resync_interval_seconds must be greater than zero");
         }
 
+        let expiry_rules = Self::compile_expiry_rules(&cli.expiry_rule, &file.expiry_rules)
+            .context("parsing expiry_rules")?;
+
+        let sensitive = Self::compile_patterns(
+            &cli.sensitive_regex
+                .iter()
+                .chain(file.sensitive_regex.iter())
+                .collect()
+        ).context("parsing sensitive_regex pattern")?;
+
         let config = Self {
             expiry: Duration::from_secs(expiry_secs),
             resync: Duration::from_secs(resync_secs),
             always_remove,
             exclude: never_remove,
+            expiry_rules,
+            sensitive,
         };
         debug!(?config, "using merged configuration");
         Ok(config)
     }
 
+    fn compile_expiry_rules(
+        cli_rules: &[String],
+        file_rules: &[ExpiryRule],
+    ) -> Result<Vec<(Regex, Duration)>> {
+        let cli_rules = cli_rules.iter().map(|raw| {
+            let (pattern, seconds) = raw
+                .rsplit_once('=')
+                .with_context(|| format!("expiry rule `{raw}` must be PATTERN=SECONDS"))?;
+            let seconds: u64 = seconds
+                .parse()
+                .with_context(|| format!("invalid seconds in expiry rule `{raw}`"))?;
+            Ok((pattern.to_string(), seconds))
+        });
+
+        let file_rules = file_rules
+            .iter()
+            .map(|rule| Ok((rule.pattern.clone(), rule.seconds)));
+
+        cli_rules
+            .chain(file_rules)
+            .map(|parsed: Result<(String, u64)>| {
+                let (pattern, seconds) = parsed?;
+                let regex = Regex::new(&pattern)
+                    .with_context(|| format!("invalid regex: {pattern}"))?;
+                Ok((regex, Duration::from_secs(seconds)))
+            })
+            .collect()
+    }
+
     fn compile_patterns(patterns: &Vec<&String>) -> Result<Vec<Regex>> {
         patterns
             .iter()
@@ -93,6 +146,20 @@ impl Config {
             .iter()
             .any(|regex| regex.is_match(content))
     }
+
+    /// Returns the expiry duration for `content`, using the first matching
+    /// `expiry_rules` entry, or falling back to the default `expiry`.
+    pub fn expiry_for(&self, content: &str) -> Duration {
+        self.expiry_rules
+            .iter()
+            .find(|(regex, _)| regex.is_match(content))
+            .map(|(_, duration)| *duration)
+            .unwrap_or(self.expiry)
+    }
+
+    pub fn is_sensitive(&self, content: &str) -> bool {
+        self.sensitive.iter().any(|regex| regex.is_match(content))
+    }
 }
 
 pub fn load_config() -> Result<Option<FileConfig>> {
@@ -122,3 +189,41 @@ pub fn load_config() -> Result<Option<FileConfig>> {
 fn default_config_path() -> Option<PathBuf> {
     config_dir().map(|dir| dir.join("klipper-timeout.toml"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with_rules(default_secs: u64, rules: &[(&str, u64)]) -> Config {
+        Config {
+            expiry: Duration::from_secs(default_secs),
+            resync: Duration::from_secs(30),
+            exclude: Vec::new(),
+            always_remove: Vec::new(),
+            expiry_rules: rules
+                .iter()
+                .map(|(pattern, secs)| (Regex::new(pattern).unwrap(), Duration::from_secs(*secs)))
+                .collect(),
+            sensitive: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn expiry_for_falls_back_to_default_when_no_rule_matches() {
+        let config = config_with_rules(600, &[("^otp:", 30)]);
+        assert_eq!(config.expiry_for("just a regular clip"), Duration::from_secs(600));
+    }
+
+    #[test]
+    fn expiry_for_uses_the_first_matching_rule() {
+        let config = config_with_rules(600, &[("^otp:", 30), ("secret", 60)]);
+        assert_eq!(config.expiry_for("otp:123456"), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn expiry_for_prefers_earlier_rule_when_patterns_overlap() {
+        // Both rules match "otp:secret", so list order decides the winner.
+        let config = config_with_rules(600, &[("secret", 60), ("^otp:", 30)]);
+        assert_eq!(config.expiry_for("otp:secret"), Duration::from_secs(60));
+    }
+}
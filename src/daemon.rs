@@ -1,12 +1,19 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use std::fmt;
+use std::future;
 use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
 use futures_util::StreamExt;
+use tokio::sync::mpsc;
 use tokio::time;
 use tracing::{debug, error, info, warn};
-use zbus::{Connection, proxy};
+use zbus::{Connection, interface, proxy};
+use zeroize::Zeroizing;
 
 use crate::config::Config;
+use crate::state::{self, PersistedState};
 
 #[proxy(
     interface = "org.kde.klipper.klipper",
@@ -27,16 +34,114 @@ pub trait Klipper {
     fn clipboard_history_updated(&self) -> zbus::Result<()>;
 }
 
-#[derive(Debug, Clone)]
+/// A runtime operation requested over the `Control` D-Bus interface, handed
+/// off to the daemon's main loop so it's only ever applied from there.
+enum ControlCommand {
+    ExpireNow,
+    PinEntry(Zeroizing<String>),
+    UnpinEntry(Zeroizing<String>),
+    SetExpiry(u64),
+    TransientCopy(Zeroizing<String>, u64),
+}
+
+/// D-Bus facing half of the control interface: just forwards requests onto
+/// `commands` for the `run` loop's `select!` to pick up.
+struct ControlHandler {
+    commands: mpsc::Sender<ControlCommand>,
+}
+
+#[interface(name = "org.klipper_timeout.Control")]
+impl ControlHandler {
+    async fn expire_now(&self) {
+        if self.commands.send(ControlCommand::ExpireNow).await.is_err() {
+            warn!("control channel closed; dropping ExpireNow request");
+        }
+    }
+
+    async fn pin_entry(&self, content: String) {
+        if self
+            .commands
+            .send(ControlCommand::PinEntry(Zeroizing::new(content)))
+            .await
+            .is_err()
+        {
+            warn!("control channel closed; dropping PinEntry request");
+        }
+    }
+
+    async fn unpin_entry(&self, content: String) {
+        if self
+            .commands
+            .send(ControlCommand::UnpinEntry(Zeroizing::new(content)))
+            .await
+            .is_err()
+        {
+            warn!("control channel closed; dropping UnpinEntry request");
+        }
+    }
+
+    async fn set_expiry(&self, seconds: u64) {
+        if self.commands.send(ControlCommand::SetExpiry(seconds)).await.is_err() {
+            warn!("control channel closed; dropping SetExpiry request");
+        }
+    }
+
+    async fn transient_copy(&self, content: String, seconds: u64) {
+        if self
+            .commands
+            .send(ControlCommand::TransientCopy(Zeroizing::new(content), seconds))
+            .await
+            .is_err()
+        {
+            warn!("control channel closed; dropping TransientCopy request");
+        }
+    }
+}
+
+#[derive(Clone)]
 struct TrackedEntry {
-    content: String,
+    id: u64,
+    content: Zeroizing<String>,
     first_seen: Instant,
+    deadline: Instant,
+    sensitive: bool,
+}
+
+/// A secret pushed via `TransientCopy`, awaiting its own clear deadline.
+/// Tracked separately from `entries` so `reconcile` can recognize it and
+/// leave it out of normal tracking without filtering it from Klipper.
+struct PendingTransient {
+    content: Zeroizing<String>,
+    deadline: Instant,
+}
+
+impl fmt::Debug for TrackedEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = f.debug_struct("TrackedEntry");
+        s.field("id", &self.id)
+            .field("first_seen", &self.first_seen)
+            .field("deadline", &self.deadline)
+            .field("sensitive", &self.sensitive);
+        if self.sensitive {
+            s.field("content", &"<redacted>");
+        } else {
+            s.field("content", &*self.content);
+        }
+        s.finish()
+    }
 }
 
 pub struct ClipboardDaemon<'conn> {
     config: Config,
+    connection: Connection,
     proxy: KlipperProxy<'conn>,
     entries: Vec<TrackedEntry>,
+    deadlines: BinaryHeap<Reverse<(Instant, u64)>>,
+    next_id: u64,
+    state: PersistedState,
+    pinned: HashSet<Zeroizing<String>>,
+    pending_transient: HashSet<Zeroizing<String>>,
+    transient_clears: Vec<PendingTransient>,
 }
 
 impl<'conn> ClipboardDaemon<'conn> {
@@ -44,10 +149,21 @@ impl<'conn> ClipboardDaemon<'conn> {
         let proxy = KlipperProxy::new(connection)
             .await
             .context("creating Klipper D-Bus proxy")?;
+        let state = state::load_state().unwrap_or_else(|err| {
+            warn!("failed to load persisted clipboard state: {err:?}");
+            PersistedState::default()
+        });
         let mut daemon = Self {
             config,
+            connection: connection.clone(),
             proxy,
             entries: Vec::new(),
+            deadlines: BinaryHeap::new(),
+            next_id: 0,
+            state,
+            pinned: HashSet::new(),
+            pending_transient: HashSet::new(),
+            transient_clears: Vec::new(),
         };
         daemon.sync_history().await?;
         Ok(daemon)
@@ -60,6 +176,17 @@ impl<'conn> ClipboardDaemon<'conn> {
             "starting clipboard expiry daemon"
         );
 
+        let (control_tx, mut control_rx) = mpsc::channel(16);
+        self.connection
+            .object_server()
+            .at("/Control", ControlHandler { commands: control_tx })
+            .await
+            .context("registering D-Bus control interface")?;
+        self.connection
+            .request_name("org.klipper_timeout.Control")
+            .await
+            .context("requesting org.klipper_timeout.Control bus name")?;
+
         let mut history_stream: Option<clipboardHistoryUpdatedStream> = match self
             .proxy
             .receive_clipboard_history_updated()
@@ -76,13 +203,27 @@ impl<'conn> ClipboardDaemon<'conn> {
 
         let mut resync_tick = time::interval(self.config.resync);
         resync_tick.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
-        let mut expiry_tick = time::interval(Duration::from_secs(1));
-        expiry_tick.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
 
         let shutdown = tokio::signal::ctrl_c();
         tokio::pin!(shutdown);
 
         loop {
+            let next_deadline = self.deadlines.peek().map(|Reverse((deadline, _))| *deadline);
+            let expiry_sleep = async {
+                match next_deadline {
+                    Some(deadline) => time::sleep_until(time::Instant::from_std(deadline)).await,
+                    None => future::pending().await,
+                }
+            };
+
+            let next_transient = self.transient_clears.iter().map(|pending| pending.deadline).min();
+            let transient_sleep = async {
+                match next_transient {
+                    Some(deadline) => time::sleep_until(time::Instant::from_std(deadline)).await,
+                    None => future::pending().await,
+                }
+            };
+
             tokio::select! {
                 res = shutdown.as_mut() => {
                     if let Err(err) = res {
@@ -96,11 +237,17 @@ impl<'conn> ClipboardDaemon<'conn> {
                         warn!("refreshing clipboard history failed: {err:?}");
                     }
                 }
-                _ = expiry_tick.tick() => {
+                _ = expiry_sleep => {
                     if let Err(err) = self.expire_due_entries().await {
                         warn!("failed to expire entries: {err:?}");
                     }
                 }
+                _ = transient_sleep => {
+                    self.clear_transient_secrets().await;
+                }
+                Some(command) = control_rx.recv() => {
+                    self.handle_control_command(command).await;
+                }
                 Some(_) = async {
                     if let Some(ref mut stream) = history_stream {
                         stream.next().await
@@ -118,6 +265,109 @@ impl<'conn> ClipboardDaemon<'conn> {
         Ok(())
     }
 
+    async fn handle_control_command(&mut self, command: ControlCommand) {
+        match command {
+            ControlCommand::ExpireNow => {
+                info!("forcing immediate expiry sweep via D-Bus control request");
+                if let Err(err) = self.force_expire().await {
+                    warn!("failed to force-expire entries: {err:?}");
+                }
+            }
+            ControlCommand::PinEntry(content) => {
+                debug!("pinning clipboard entry via D-Bus control request");
+                self.pinned.insert(content);
+            }
+            ControlCommand::UnpinEntry(content) => {
+                debug!("unpinning clipboard entry via D-Bus control request");
+                self.pinned.remove(&content);
+                self.reschedule_unpinned_entry(&content);
+            }
+            ControlCommand::SetExpiry(seconds) => {
+                if seconds == 0 {
+                    warn!("ignoring SetExpiry request: expiry must be greater than zero");
+                    return;
+                }
+                info!(seconds, "updating default expiry via D-Bus control request");
+                self.config.expiry = Duration::from_secs(seconds);
+                self.reschedule_default_expiry_entries();
+            }
+            ControlCommand::TransientCopy(content, seconds) => {
+                info!(seconds, "pushing transient clipboard secret via D-Bus control request");
+                if let Err(err) = self.proxy.set_clipboard_contents(&content).await {
+                    warn!("failed to set transient clipboard contents: {err:?}");
+                    return;
+                }
+                let deadline = Instant::now() + Duration::from_secs(seconds);
+                self.pending_transient.insert(content.clone());
+                self.transient_clears
+                    .push(PendingTransient { content, deadline });
+            }
+        }
+    }
+
+    /// Recomputes and re-queues the deadline of every tracked entry that
+    /// isn't overridden by an `expiry_rule`, so a live `SetExpiry` takes
+    /// effect on clips already in history instead of only ones copied
+    /// afterward.
+    fn reschedule_default_expiry_entries(&mut self) {
+        for entry in &mut self.entries {
+            let overridden = self
+                .config
+                .expiry_rules
+                .iter()
+                .any(|(regex, _)| regex.is_match(&entry.content));
+            if overridden {
+                continue;
+            }
+
+            let new_deadline = entry.first_seen + self.config.expiry;
+            entry.deadline = new_deadline;
+            self.deadlines.push(Reverse((new_deadline, entry.id)));
+        }
+    }
+
+    /// Gives a just-unpinned entry a fresh, prompt deadline instead of
+    /// leaving it with whatever "recheck" deadline `expire_due_entries`
+    /// last gave it while it was still exempt, so unpinning takes effect
+    /// right away rather than up to one more full expiry cycle later.
+    fn reschedule_unpinned_entry(&mut self, content: &Zeroizing<String>) {
+        let Some(entry) = self.entries.iter_mut().find(|entry| entry.content == *content) else {
+            return;
+        };
+
+        let now = Instant::now();
+        let natural_deadline = entry.first_seen + self.config.expiry_for(&entry.content);
+        let new_deadline = natural_deadline.max(now);
+        entry.deadline = new_deadline;
+        self.deadlines.push(Reverse((new_deadline, entry.id)));
+    }
+
+    /// Once a pending `TransientCopy` deadline has passed, restores the
+    /// clipboard to just the currently tracked entries. The transient secret
+    /// itself was never added to `self.entries`, so `rewrite_history` drops
+    /// it while putting every other tracked clip back.
+    async fn clear_transient_secrets(&mut self) {
+        let now = Instant::now();
+        let mut due = false;
+
+        let pending_transient = &mut self.pending_transient;
+        self.transient_clears.retain(|pending| {
+            if pending.deadline > now {
+                return true;
+            }
+            pending_transient.remove(&pending.content);
+            due = true;
+            false
+        });
+
+        if due {
+            info!("restoring tracked clipboard entries after transient secret expired");
+            if let Err(err) = self.rewrite_history().await {
+                warn!("failed to restore clipboard after transient secret: {err:?}");
+            }
+        }
+    }
+
     async fn sync_history(&mut self) -> Result<()> {
         let history = self
             .proxy
@@ -139,8 +389,10 @@ impl<'conn> ClipboardDaemon<'conn> {
         let mut next = Vec::with_capacity(history.len());
         let now = Instant::now();
         let mut filtered = false;
+        let mut tracked_changed = false;
 
         for content in history {
+            let content = Zeroizing::new(content);
             if self.config.should_always_remove(&content) {
                 info!("removing clipboard entry that matches always_remove_patterns");
                 filtered = true;
@@ -154,44 +406,112 @@ impl<'conn> ClipboardDaemon<'conn> {
             {
                 matched[idx] = true;
                 next.push(entry.clone());
+            } else if self.pending_transient.contains(&content) {
+                // Still a live TransientCopy: leave it out of tracking so it
+                // can't be absorbed into the regular expiry scheme, but
+                // don't mark `filtered` since it's still meant to be visible
+                // in Klipper until its own timer clears it.
+                debug!("leaving pending transient secret out of tracked clipboard history");
             } else {
                 debug!("tracking new clipboard entry");
+                tracked_changed = true;
+                let id = self.next_id;
+                self.next_id += 1;
+                let sensitive = self.config.is_sensitive(&content);
+                let lookup_key = if sensitive {
+                    state::hash_content(&content)
+                } else {
+                    content.as_str().to_string()
+                };
+                let first_seen = self.state.instant_for(&lookup_key).unwrap_or(now);
+                let deadline = first_seen + self.config.expiry_for(&content);
+                self.deadlines.push(Reverse((deadline, id)));
                 next.push(TrackedEntry {
+                    id,
                     content,
-                    first_seen: now,
+                    first_seen,
+                    deadline,
+                    sensitive,
                 });
             }
         }
 
+        // A previously tracked entry that didn't get matched this round is
+        // gone from history (removed by Klipper, or filtered above), which
+        // also changes what the persisted map should contain.
+        let entry_removed = matched.iter().any(|was_matched| !was_matched);
+
         self.entries = next;
+        if tracked_changed || entry_removed {
+            self.persist_state();
+        }
         filtered
     }
 
+    /// Rebuilds the persisted first-seen map from the current entries and
+    /// writes it to disk, so a restart can restore accurate ages. Sensitive
+    /// entries are persisted under a hash of their content rather than the
+    /// plaintext, so they still expire on schedule across a restart without
+    /// writing their content to disk.
+    fn persist_state(&mut self) {
+        let mut state = PersistedState::default();
+        for entry in &self.entries {
+            let key = if entry.sensitive {
+                state::hash_content(&entry.content)
+            } else {
+                entry.content.as_str().to_string()
+            };
+            state.record(key, entry.first_seen);
+        }
+        self.state = state;
+
+        if let Err(err) = state::save_state(&self.state) {
+            warn!("failed to persist clipboard state: {err:?}");
+        }
+    }
+
     async fn expire_due_entries(&mut self) -> Result<()> {
-        if self.entries.is_empty() {
-            return Ok(());
+        let now = Instant::now();
+        let expired = pop_expired_entries(
+            &mut self.entries,
+            &mut self.deadlines,
+            &self.config,
+            &self.pinned,
+            now,
+        );
+
+        for entry in &expired {
+            info!(age = ?entry.first_seen.elapsed(), "expiring clipboard entry");
         }
 
-        let expiry = self.config.expiry;
+        if !expired.is_empty() {
+            self.persist_state();
+            self.rewrite_history()
+                .await
+                .context("rewriting clipboard history")?;
+        }
+
+        Ok(())
+    }
+
+    /// Immediately expires every entry that isn't exempt, ignoring their
+    /// deadlines, for the D-Bus `ExpireNow` control request.
+    async fn force_expire(&mut self) -> Result<()> {
         let mut changed = false;
 
+        let config = &self.config;
+        let pinned = &self.pinned;
         self.entries.retain(|entry| {
-            let expired = entry.first_seen.elapsed() >= expiry;
-            if expired && self.config.should_never_remove(&entry.content) {
-                debug!("skipping expiry for clipboard entry that matches never_remove_patterns");
+            if is_exempt_from_expiry(config, pinned, &entry.content) {
                 return true;
             }
-            if expired {
-                info!(
-                    age = ?entry.first_seen.elapsed(),
-                    "expiring clipboard entry",
-                );
-                changed = true;
-            }
-            !expired
+            info!(age = ?entry.first_seen.elapsed(), "force-expiring clipboard entry");
+            changed = true;
+            false
         });
 
         if changed {
+            self.persist_state();
             self.rewrite_history()
                 .await
                 .context("rewriting clipboard history")?;
@@ -216,3 +536,148 @@ impl<'conn> ClipboardDaemon<'conn> {
         Ok(())
     }
 }
+
+fn is_exempt_from_expiry(
+    config: &Config,
+    pinned: &HashSet<Zeroizing<String>>,
+    content: &Zeroizing<String>,
+) -> bool {
+    config.should_never_remove(content) || pinned.contains(content)
+}
+
+/// Pops every heap record whose deadline has passed, rescheduling entries
+/// that are currently exempt instead of dropping them, and returns the
+/// entries that actually expired. Kept free of D-Bus I/O (unlike
+/// `ClipboardDaemon::expire_due_entries`, which wraps this) so the heap
+/// lazy-deletion scheme can be exercised directly in tests.
+fn pop_expired_entries(
+    entries: &mut Vec<TrackedEntry>,
+    deadlines: &mut BinaryHeap<Reverse<(Instant, u64)>>,
+    config: &Config,
+    pinned: &HashSet<Zeroizing<String>>,
+    now: Instant,
+) -> Vec<TrackedEntry> {
+    let mut expired = Vec::new();
+
+    while let Some(&Reverse((deadline, id))) = deadlines.peek() {
+        if deadline > now {
+            break;
+        }
+        deadlines.pop();
+
+        // The heap can outlive its entry: reconcile/sync_history rebuild
+        // `entries` wholesale, so a popped (deadline, id) may no longer
+        // match anything. Ignore it rather than expiring the wrong entry.
+        let Some(pos) = entries
+            .iter()
+            .position(|entry| entry.id == id && entry.deadline == deadline)
+        else {
+            continue;
+        };
+
+        if is_exempt_from_expiry(config, pinned, &entries[pos].content) {
+            // Exemptions (regex or a live Pin) are reversible, so the
+            // entry needs a fresh heap record rather than being dropped
+            // from scheduling entirely - otherwise an UnpinEntry later
+            // would leave it with no deadline to ever expire against.
+            debug!("skipping expiry for clipboard entry that is exempt from expiry; rescheduling recheck");
+            let new_deadline = now + config.expiry_for(&entries[pos].content);
+            entries[pos].deadline = new_deadline;
+            deadlines.push(Reverse((new_deadline, id)));
+            continue;
+        }
+
+        expired.push(entries.remove(pos));
+    }
+
+    expired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u64, content: &str, deadline: Instant) -> TrackedEntry {
+        TrackedEntry {
+            id,
+            content: Zeroizing::new(content.to_string()),
+            first_seen: deadline,
+            deadline,
+            sensitive: false,
+        }
+    }
+
+    fn test_config() -> Config {
+        Config {
+            expiry: Duration::from_secs(600),
+            resync: Duration::from_secs(30),
+            exclude: Vec::new(),
+            always_remove: Vec::new(),
+            expiry_rules: Vec::new(),
+            sensitive: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn pop_expired_entries_removes_only_due_entries() {
+        let now = Instant::now();
+        let past = now - Duration::from_secs(1);
+        let future = now + Duration::from_secs(60);
+
+        let mut entries = vec![entry(1, "stale", past), entry(2, "fresh", future)];
+        let mut deadlines = BinaryHeap::new();
+        deadlines.push(Reverse((past, 1)));
+        deadlines.push(Reverse((future, 2)));
+        let config = test_config();
+        let pinned = HashSet::new();
+
+        let expired = pop_expired_entries(&mut entries, &mut deadlines, &config, &pinned, now);
+
+        assert_eq!(expired.len(), 1);
+        assert_eq!(*expired[0].content, "stale");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(*entries[0].content, "fresh");
+    }
+
+    #[test]
+    fn pop_expired_entries_ignores_stale_heap_records() {
+        let now = Instant::now();
+        let past = now - Duration::from_secs(1);
+
+        // No matching entry for this (deadline, id) pair - simulates a
+        // reconcile that rebuilt `entries` after the heap record was pushed.
+        let mut entries: Vec<TrackedEntry> = Vec::new();
+        let mut deadlines = BinaryHeap::new();
+        deadlines.push(Reverse((past, 42)));
+        let config = test_config();
+        let pinned = HashSet::new();
+
+        let expired = pop_expired_entries(&mut entries, &mut deadlines, &config, &pinned, now);
+
+        assert!(expired.is_empty());
+        assert!(deadlines.is_empty());
+    }
+
+    #[test]
+    fn pop_expired_entries_reschedules_pinned_entries_instead_of_dropping_them() {
+        let now = Instant::now();
+        let past = now - Duration::from_secs(1);
+
+        let mut entries = vec![entry(1, "secret", past)];
+        let mut deadlines = BinaryHeap::new();
+        deadlines.push(Reverse((past, 1)));
+        let config = test_config();
+        let mut pinned = HashSet::new();
+        pinned.insert(Zeroizing::new("secret".to_string()));
+
+        let expired = pop_expired_entries(&mut entries, &mut deadlines, &config, &pinned, now);
+
+        assert!(expired.is_empty());
+        assert_eq!(entries.len(), 1, "pinned entry must stay tracked");
+        assert_eq!(
+            deadlines.len(),
+            1,
+            "a fresh deadline must be requeued so unpinning later can still expire it"
+        );
+    }
+}
@@ -1,11 +1,12 @@
 mod cli;
 mod config;
 mod daemon;
+mod state;
 
 use anyhow::{Context, Result};
 use cli::{Cli, init_tracing};
 use config::{Config, load_config};
-use daemon::{ClipboardDaemon, KlipperProxy};
+use daemon::ClipboardDaemon;
 use zbus::Connection;
 
 #[tokio::main]
@@ -19,10 +20,7 @@ async fn main() -> Result<()> {
     let connection = Connection::session()
         .await
         .context("connecting to D-Bus session bus")?;
-    let proxy = KlipperProxy::new(&connection)
-        .await
-        .context("creating Klipper D-Bus proxy")?;
 
-    let mut daemon = ClipboardDaemon::new(config, proxy).await?;
+    let mut daemon = ClipboardDaemon::new(config, &connection).await?;
     daemon.run().await
 }
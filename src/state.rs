@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use dirs::state_dir;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+/// On-disk record of when each tracked clipboard entry was first observed,
+/// so that restarting the daemon doesn't give every item a fresh full
+/// lifetime.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PersistedState {
+    first_seen_unix_secs: HashMap<String, u64>,
+}
+
+impl PersistedState {
+    /// Looks up `content`'s saved first-seen time and translates it into an
+    /// `Instant` comparable to the current process's clock.
+    pub fn instant_for(&self, content: &str) -> Option<Instant> {
+        let secs = *self.first_seen_unix_secs.get(content)?;
+        let saved = UNIX_EPOCH + Duration::from_secs(secs);
+        let elapsed = SystemTime::now().duration_since(saved).unwrap_or_default();
+        Some(Instant::now().checked_sub(elapsed).unwrap_or_else(Instant::now))
+    }
+
+    pub fn record(&mut self, content: String, first_seen: Instant) {
+        let wall_clock = SystemTime::now()
+            .checked_sub(first_seen.elapsed())
+            .unwrap_or_else(SystemTime::now);
+        let secs = wall_clock
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        self.first_seen_unix_secs.insert(content, secs);
+    }
+}
+
+pub fn load_state() -> Result<PersistedState> {
+    let path = match default_state_path() {
+        Some(path) => path,
+        None => {
+            warn!("could not determine path for state file");
+            return Ok(PersistedState::default());
+        }
+    };
+
+    if !path.exists() {
+        debug!("state file does not exist: {:?}", path);
+        return Ok(PersistedState::default());
+    } else {
+        debug!("reading from state file: {:?}", path);
+    }
+
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("reading state file at {}", path.display()))?;
+
+    let parsed = serde_json::from_str(&content)
+        .with_context(|| format!("parsing state file at {}", path.display()))?;
+    Ok(parsed)
+}
+
+pub fn save_state(state: &PersistedState) -> Result<()> {
+    let path = match default_state_path() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("creating state directory {}", parent.display()))?;
+    }
+
+    let serialized = serde_json::to_string(state).context("serializing clipboard state")?;
+    fs::write(&path, serialized)
+        .with_context(|| format!("writing state file at {}", path.display()))?;
+    Ok(())
+}
+
+fn default_state_path() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("klipper-timeout-state.json"))
+}
+
+/// Derives the key used to persist a sensitive entry's first-seen time,
+/// so its plaintext never gets written to the state file while its
+/// lifetime can still survive a restart.
+pub fn hash_content(content: &str) -> String {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    content.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instant_for_round_trips_through_a_record() {
+        let mut state = PersistedState::default();
+        let first_seen = Instant::now() - Duration::from_secs(120);
+        state.record("clip".to_string(), first_seen);
+
+        let restored = state.instant_for("clip").expect("recorded content must be found");
+
+        // The record/instant_for pair bounces through a whole-second SystemTime,
+        // so allow a small tolerance rather than expecting exact equality.
+        let drift = if restored > first_seen {
+            restored - first_seen
+        } else {
+            first_seen - restored
+        };
+        assert!(drift < Duration::from_secs(2), "drift was {drift:?}");
+    }
+
+    #[test]
+    fn instant_for_returns_none_for_unknown_content() {
+        let state = PersistedState::default();
+        assert!(state.instant_for("never recorded").is_none());
+    }
+
+    #[test]
+    fn hash_content_is_deterministic_and_distinguishes_inputs() {
+        assert_eq!(hash_content("same"), hash_content("same"));
+        assert_ne!(hash_content("one"), hash_content("other"));
+    }
+}